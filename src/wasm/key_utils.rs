@@ -26,13 +26,17 @@
 
 use crate::{
     common::Blake256,
-    keys::PublicKey,
+    keys::{PublicKey, SecretKey},
     ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
 };
 use blake2::Digest;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::VartimeMultiscalarMul};
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
-use tari_utilities::hex::{from_hex, Hex};
+use tari_utilities::{
+    hex::{from_hex, Hex},
+    ByteArray,
+};
 use wasm_bindgen::prelude::*;
 
 #[derive(Serialize, Deserialize)]
@@ -41,6 +45,23 @@ pub struct SignatureVerifyResult {
     pub error: String,
 }
 
+/// A single `(public nonce, signature, public key, message)` tuple to be checked by [check_signature_batch].
+#[derive(Serialize, Deserialize)]
+pub struct SignatureBatchTuple {
+    pub public_nonce: String,
+    pub signature: String,
+    pub public_key: String,
+    pub message: String,
+}
+
+/// The result of a [check_signature_batch] call: an overall boolean (true iff every signature is valid) plus a
+/// per-entry breakdown.
+#[derive(Serialize, Deserialize)]
+pub struct SignatureVerifyBatchResult {
+    pub result: bool,
+    pub results: Vec<SignatureVerifyResult>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SignResult {
     pub public_nonce: Option<String>,
@@ -86,7 +107,10 @@ pub fn pubkey_from_secret(k: &str) -> Option<String> {
     }
 }
 
-/// Generate a Schnorr signature of the message using the given private key
+/// Generate a Schnorr signature of the message using the given private key. The challenge binds the signer's
+/// public key and public nonce as well as the message (`e = H(P || R || msg)`), which prevents key-substitution
+/// and related forgery attacks. Use [sign_legacy] if you need a signature that verifies against
+/// [check_signature_legacy] instead.
 #[wasm_bindgen]
 pub fn sign(private_key: &str, msg: &str) -> JsValue {
     let mut result = SignResult::default();
@@ -101,6 +125,25 @@ pub fn sign(private_key: &str, msg: &str) -> JsValue {
     JsValue::from_serde(&result).unwrap()
 }
 
+/// Generate a Schnorr signature of the message using the given private key, with the challenge computed as
+/// `e = H(msg)` only. This does NOT bind the public key or public nonce into the challenge, and is kept only for
+/// callers that must remain compatible with signatures produced before the challenge was domain-separated. New
+/// code should use [sign] instead.
+#[wasm_bindgen]
+pub fn sign_legacy(private_key: &str, msg: &str) -> JsValue {
+    let mut result = SignResult::default();
+    let k = match RistrettoSecretKey::from_hex(private_key) {
+        Ok(k) => k,
+        _ => {
+            result.error = "Invalid private key".to_string();
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    let e = Blake256::digest(msg.as_bytes());
+    sign_with_key(&k, e.as_slice(), None, &mut result);
+    JsValue::from_serde(&result).unwrap()
+}
+
 /// Generate a Schnorr signature of a challenge (that has already been hashed) using the given private
 /// key and a specified private nonce. DO NOT reuse nonces. This method is provide for cases where a
 /// public nonce has been used
@@ -134,6 +177,19 @@ pub fn sign_challenge_with_nonce(private_key: &str, private_nonce: &str, challen
     JsValue::from_serde(&result).unwrap()
 }
 
+/// Computes the canonical domain-separated Schnorr challenge `e = H(P || R || msg)`, binding the public key and
+/// public nonce into the challenge. This matches the construction used elsewhere in the Tari codebase and
+/// prevents key-substitution forgeries.
+#[allow(non_snake_case)]
+pub(crate) fn domain_separated_challenge(P: &RistrettoPublicKey, R: &RistrettoPublicKey, msg: &[u8]) -> Vec<u8> {
+    Blake256::new()
+        .chain(P.as_bytes())
+        .chain(R.as_bytes())
+        .chain(msg)
+        .finalize()
+        .to_vec()
+}
+
 pub(crate) fn sign_message_with_key(
     k: &RistrettoSecretKey,
     msg: &str,
@@ -141,8 +197,13 @@ pub(crate) fn sign_message_with_key(
     result: &mut SignResult,
 )
 {
-    let e = Blake256::digest(msg.as_bytes());
-    sign_with_key(k, e.as_slice(), r, result)
+    let (r, R) = match r {
+        Some(r) => (r.clone(), RistrettoPublicKey::from_secret_key(r)),
+        None => RistrettoPublicKey::random_keypair(&mut OsRng),
+    };
+    let P = RistrettoPublicKey::from_secret_key(k);
+    let e = domain_separated_challenge(&P, &R, msg.as_bytes());
+    sign_with_key(k, e.as_slice(), Some(&r), result)
 }
 
 #[allow(non_snake_case)]
@@ -163,7 +224,21 @@ pub(crate) fn sign_with_key(k: &RistrettoSecretKey, e: &[u8], r: Option<&Ristret
     result.signature = Some(sig.get_signature().to_hex());
 }
 
-/// Checks the validity of a Schnorr signature
+#[allow(non_snake_case)]
+fn parse_signature_parts(
+    pub_nonce: &str,
+    signature: &str,
+    pub_key: &str,
+) -> Result<(RistrettoPublicKey, RistrettoSecretKey, RistrettoPublicKey), String> {
+    let R = RistrettoPublicKey::from_hex(pub_nonce).map_err(|_| format!("{} is not a valid public nonce", pub_nonce))?;
+    let P = RistrettoPublicKey::from_hex(pub_key).map_err(|_| format!("{} is not a valid public key", pub_key))?;
+    let s = RistrettoSecretKey::from_hex(signature)
+        .map_err(|_| format!("{} is not a valid hex representation of a signature", signature))?;
+    Ok((R, s, P))
+}
+
+/// Checks the validity of a Schnorr signature produced by [sign]. The challenge binds the public key and public
+/// nonce as well as the message (`e = H(P || R || msg)`).
 #[allow(non_snake_case)]
 #[wasm_bindgen]
 pub fn check_signature(pub_nonce: &str, signature: &str, pub_key: &str, msg: &str) -> JsValue {
@@ -172,30 +247,564 @@ pub fn check_signature(pub_nonce: &str, signature: &str, pub_key: &str, msg: &st
         error: "".into(),
     };
 
-    let R = match RistrettoPublicKey::from_hex(pub_nonce) {
-        Ok(n) => n,
-        Err(_) => {
-            result.error = format!("{} is not a valid public nonce", pub_nonce);
+    let (R, s, P) = match parse_signature_parts(pub_nonce, signature, pub_key) {
+        Ok(parts) => parts,
+        Err(e) => {
+            result.error = e;
             return JsValue::from_serde(&result).unwrap();
         },
     };
 
-    let P = RistrettoPublicKey::from_hex(pub_key);
-    if P.is_err() {
-        result.error = format!("{} is not a valid public key", pub_key);
-        return JsValue::from_serde(&result).unwrap();
-    }
-    let P = P.unwrap();
+    let sig = RistrettoSchnorr::new(R.clone(), s);
+    let e = domain_separated_challenge(&P, &R, msg.as_bytes());
+    result.result = sig.verify_challenge(&P, e.as_slice());
+    JsValue::from_serde(&result).unwrap()
+}
 
-    let s = RistrettoSecretKey::from_hex(signature);
-    if s.is_err() {
-        result.error = format!("{} is not a valid hex representation of a signature", signature);
-        return JsValue::from_serde(&result).unwrap();
-    }
-    let s = s.unwrap();
+/// Checks the validity of a Schnorr signature produced by [sign_legacy], where the challenge is `e = H(msg)` only
+/// and does not bind the public key or public nonce. Retained for backwards compatibility; prefer
+/// [check_signature] for new code.
+#[allow(non_snake_case)]
+#[wasm_bindgen]
+pub fn check_signature_legacy(pub_nonce: &str, signature: &str, pub_key: &str, msg: &str) -> JsValue {
+    let mut result = SignatureVerifyResult {
+        result: false,
+        error: "".into(),
+    };
+
+    let (R, s, P) = match parse_signature_parts(pub_nonce, signature, pub_key) {
+        Ok(parts) => parts,
+        Err(e) => {
+            result.error = e;
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
 
     let sig = RistrettoSchnorr::new(R, s);
     let msg = Blake256::digest(msg.as_bytes());
     result.result = sig.verify_challenge(&P, msg.as_slice());
     JsValue::from_serde(&result).unwrap()
 }
+
+fn scalar_from_secret_key(k: &RistrettoSecretKey) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(k.as_bytes());
+    Scalar::from_bits(bytes)
+}
+
+fn point_from_public_key(p: &RistrettoPublicKey) -> RistrettoPoint {
+    curve25519_dalek::ristretto::CompressedRistretto::from_slice(p.as_bytes())
+        .decompress()
+        .expect("a RistrettoPublicKey is always a valid compressed point")
+}
+
+fn random_nonzero_scalar() -> Scalar {
+    loop {
+        let s = Scalar::random(&mut OsRng);
+        if s != Scalar::zero() {
+            return s;
+        }
+    }
+}
+
+/// Verifies the aggregate equation `(Σ z_i·s_i)·G == Σ z_i·R_i + Σ z_i·e_i·P_i` for random non-zero `z_i`. If this
+/// holds, every `(R_i, s_i, P_i, e_i)` is a valid Schnorr signature with overwhelming probability.
+#[allow(non_snake_case)]
+fn verify_batch_aggregate(entries: &[(RistrettoPublicKey, RistrettoSecretKey, RistrettoPublicKey, String)]) -> bool {
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+
+    let z: Vec<Scalar> = entries.iter().map(|_| random_nonzero_scalar()).collect();
+    let mut s_agg = Scalar::zero();
+    let mut scalars = Vec::with_capacity(entries.len() * 2);
+    let mut points = Vec::with_capacity(entries.len() * 2);
+
+    for (z_i, (R, s, P, msg)) in z.iter().zip(entries.iter()) {
+        s_agg += z_i * scalar_from_secret_key(s);
+        let e = domain_separated_challenge(P, R, msg.as_bytes());
+        let e_scalar =
+            Scalar::from_bytes_mod_order(e.try_into().expect("Blake256 digest is always 32 bytes"));
+        scalars.push(*z_i);
+        points.push(point_from_public_key(R));
+        scalars.push(z_i * e_scalar);
+        points.push(point_from_public_key(P));
+    }
+
+    let lhs = &s_agg * &RISTRETTO_BASEPOINT_TABLE;
+    let rhs = RistrettoPoint::vartime_multiscalar_mul(scalars.iter(), points.iter());
+    lhs == rhs
+}
+
+/// Verifies a batch of `(public nonce, signature, public key, message)` tuples using the random linear
+/// combination technique: a single aggregate check is attempted first, and if it fails, each signature is
+/// verified individually to report which ones are invalid. This is considerably faster than verifying each
+/// signature individually when most or all of the batch is valid, which is the common case for wallets and light
+/// clients validating blocks.
+#[wasm_bindgen]
+pub fn check_signature_batch(signatures: JsValue) -> JsValue {
+    let tuples: Vec<SignatureBatchTuple> = match signatures.into_serde() {
+        Ok(t) => t,
+        Err(e) => {
+            let result = SignatureVerifyBatchResult {
+                result: false,
+                results: vec![SignatureVerifyResult {
+                    result: false,
+                    error: format!("Could not parse signature batch: {}", e),
+                }],
+            };
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+
+    let mut entries = Vec::with_capacity(tuples.len());
+    let mut results = Vec::with_capacity(tuples.len());
+    let mut all_parsed = true;
+    for t in &tuples {
+        match parse_signature_parts(&t.public_nonce, &t.signature, &t.public_key) {
+            Ok((R, s, P)) => {
+                entries.push((R, s, P, t.message.clone()));
+                results.push(SignatureVerifyResult {
+                    result: false,
+                    error: "".into(),
+                });
+            },
+            Err(e) => {
+                all_parsed = false;
+                results.push(SignatureVerifyResult { result: false, error: e });
+            },
+        }
+    }
+
+    if all_parsed && verify_batch_aggregate(&entries) {
+        for r in results.iter_mut() {
+            r.result = true;
+        }
+        let result = SignatureVerifyBatchResult { result: true, results };
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    // The aggregate check failed (or some tuples didn't even parse): fall back to verifying each signature
+    // individually to determine which indices are invalid.
+    let mut all_valid = all_parsed;
+    let mut entries_iter = entries.into_iter();
+    for r in results.iter_mut() {
+        if !r.error.is_empty() {
+            all_valid = false;
+            continue;
+        }
+        let (R, s, P, msg) = entries_iter.next().expect("one entry per successfully parsed tuple");
+        let sig = RistrettoSchnorr::new(R.clone(), s);
+        let e = domain_separated_challenge(&P, &R, msg.as_bytes());
+        let ok = sig.verify_challenge(&P, e.as_slice());
+        r.result = ok;
+        if !ok {
+            r.error = "Invalid signature".to_string();
+            all_valid = false;
+        }
+    }
+
+    let result = SignatureVerifyBatchResult {
+        result: all_valid,
+        results,
+    };
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Generates a fresh randomizer scalar `α`, suitable for use with [randomize_secret], [randomize_public] and
+/// [sign_randomized]. Always returns `Some`.
+#[wasm_bindgen]
+pub fn generate_randomizer() -> Option<String> {
+    Some(RistrettoSecretKey::random(&mut OsRng).to_hex())
+}
+
+/// Randomizes a secret key `k` with the randomizer `α`, returning `k + α`. This mirrors the spend-authorization
+/// key randomization used in RedDSA/Zcash: the result is an ordinary secret key that signs like any other, but a
+/// verifier handed only [randomize_public]'s output can't link signatures back to `k`.
+#[wasm_bindgen]
+pub fn randomize_secret(secret_key: &str, randomizer: &str) -> Option<String> {
+    let k = RistrettoSecretKey::from_hex(secret_key).ok()?;
+    let alpha = RistrettoSecretKey::from_hex(randomizer).ok()?;
+    Some((k + alpha).to_hex())
+}
+
+/// Randomizes a public key `P` with the randomizer `α`, returning `P + α·G`. This is the public counterpart of
+/// [randomize_secret]: a signer randomizes their keypair with the same `α` on both sides, and hands only the
+/// randomized public key to the verifier.
+#[allow(non_snake_case)]
+#[wasm_bindgen]
+pub fn randomize_public(public_key: &str, randomizer: &str) -> Option<String> {
+    let P = RistrettoPublicKey::from_hex(public_key).ok()?;
+    let alpha = RistrettoSecretKey::from_hex(randomizer).ok()?;
+    Some((P + RistrettoPublicKey::from_secret_key(&alpha)).to_hex())
+}
+
+/// Signs `msg` with the secret key `k` randomized by `α` (i.e. `k + α`). The challenge binds the randomized
+/// public key, so the signature only verifies (via [check_signature]) against [randomize_public]'s output, never
+/// against the base public key `P = k·G`.
+#[wasm_bindgen]
+pub fn sign_randomized(private_key: &str, randomizer: &str, msg: &str) -> JsValue {
+    let mut result = SignResult::default();
+    let k = match RistrettoSecretKey::from_hex(private_key) {
+        Ok(k) => k,
+        _ => {
+            result.error = "Invalid private key".to_string();
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    let alpha = match RistrettoSecretKey::from_hex(randomizer) {
+        Ok(a) => a,
+        _ => {
+            result.error = "Invalid randomizer".to_string();
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    sign_message_with_key(&(k + alpha), msg, None, &mut result);
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// The length in bytes of a canonical keypair buffer: a 32-byte secret key scalar followed by a 32-byte
+/// compressed Ristretto public key.
+const KEYPAIR_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize)]
+pub struct KeyPairResult {
+    pub secret_key: Option<String>,
+    pub public_key: Option<String>,
+    pub error: String,
+}
+
+fn keypair_from_bytes_inner(bytes: &[u8]) -> Result<(RistrettoSecretKey, RistrettoPublicKey), String> {
+    if bytes.len() != KEYPAIR_LENGTH {
+        return Err(format!("A keypair buffer must be {} bytes long, got {}", KEYPAIR_LENGTH, bytes.len()));
+    }
+    let k = RistrettoSecretKey::from_bytes(&bytes[..32]).map_err(|e| format!("Invalid secret key: {}", e))?;
+    let given_public_key =
+        RistrettoPublicKey::from_bytes(&bytes[32..]).map_err(|e| format!("Invalid public key: {}", e))?;
+    let derived_public_key = RistrettoPublicKey::from_secret_key(&k);
+    if given_public_key != derived_public_key {
+        return Err("Public key does not match the secret key".to_string());
+    }
+    Ok((k, derived_public_key))
+}
+
+/// Serializes a secret key as the canonical keypair buffer: the secret key bytes followed by the 32-byte
+/// compressed Ristretto public key derived from it. This gives a stable, length-checked wallet backup format,
+/// instead of a loosely-typed hex tuple as returned by [generate_keypair].
+#[wasm_bindgen]
+pub fn keypair_to_bytes(secret_key: &str) -> Option<Vec<u8>> {
+    let k = RistrettoSecretKey::from_hex(secret_key).ok()?;
+    let p = RistrettoPublicKey::from_secret_key(&k);
+    let mut bytes = Vec::with_capacity(KEYPAIR_LENGTH);
+    bytes.extend_from_slice(k.as_bytes());
+    bytes.extend_from_slice(p.as_bytes());
+    Some(bytes)
+}
+
+/// Hex-encoded variant of [keypair_to_bytes].
+#[wasm_bindgen]
+pub fn keypair_to_hex(secret_key: &str) -> Option<String> {
+    keypair_to_bytes(secret_key).map(|bytes| bytes.to_hex())
+}
+
+/// Restores a secret/public keypair from the canonical buffer produced by [keypair_to_bytes], validating that the
+/// embedded public key actually matches `from_secret_key(secret)`. Returns a structured error if the buffer is the
+/// wrong length, contains invalid key bytes, or the public key doesn't match.
+#[wasm_bindgen]
+pub fn keypair_from_bytes(bytes: &[u8]) -> JsValue {
+    let mut result = KeyPairResult {
+        secret_key: None,
+        public_key: None,
+        error: "".into(),
+    };
+    match keypair_from_bytes_inner(bytes) {
+        Ok((k, p)) => {
+            result.secret_key = Some(k.to_hex());
+            result.public_key = Some(p.to_hex());
+        },
+        Err(e) => result.error = e,
+    }
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Hex-encoded variant of [keypair_from_bytes].
+#[wasm_bindgen]
+pub fn keypair_from_hex(hex: &str) -> JsValue {
+    let mut result = KeyPairResult {
+        secret_key: None,
+        public_key: None,
+        error: "".into(),
+    };
+    let bytes = match from_hex(hex) {
+        Ok(b) => b,
+        Err(_) => {
+            result.error = "Input was not valid hex".to_string();
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    match keypair_from_bytes_inner(&bytes) {
+        Ok((k, p)) => {
+            result.secret_key = Some(k.to_hex());
+            result.public_key = Some(p.to_hex());
+        },
+        Err(e) => result.error = e,
+    }
+    JsValue::from_serde(&result).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn sign_and_check_signature_round_trip() {
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let msg = "a message bound to its signer";
+
+        let signed: SignResult = sign(&k.to_hex(), msg).into_serde().unwrap();
+        let result: SignatureVerifyResult = check_signature(
+            signed.public_nonce.as_ref().unwrap(),
+            signed.signature.as_ref().unwrap(),
+            &p.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(result.result, "a signature produced by sign() must verify via check_signature(): {}", result.error);
+    }
+
+    #[wasm_bindgen_test]
+    fn sign_legacy_and_check_signature_legacy_round_trip() {
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let msg = "a message signed the old way";
+
+        let signed: SignResult = sign_legacy(&k.to_hex(), msg).into_serde().unwrap();
+        let result: SignatureVerifyResult = check_signature_legacy(
+            signed.public_nonce.as_ref().unwrap(),
+            signed.signature.as_ref().unwrap(),
+            &p.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            result.result,
+            "a signature produced by sign_legacy() must verify via check_signature_legacy(): {}",
+            result.error
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn the_two_challenge_domains_do_not_cross_verify() {
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let msg = "domain separation matters";
+
+        let signed: SignResult = sign(&k.to_hex(), msg).into_serde().unwrap();
+        let cross_checked: SignatureVerifyResult = check_signature_legacy(
+            signed.public_nonce.as_ref().unwrap(),
+            signed.signature.as_ref().unwrap(),
+            &p.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            !cross_checked.result,
+            "a signature bound to P and R via sign() must not verify under the H(msg)-only legacy challenge"
+        );
+
+        let signed_legacy: SignResult = sign_legacy(&k.to_hex(), msg).into_serde().unwrap();
+        let cross_checked_new: SignatureVerifyResult = check_signature(
+            signed_legacy.public_nonce.as_ref().unwrap(),
+            signed_legacy.signature.as_ref().unwrap(),
+            &p.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            !cross_checked_new.result,
+            "a signature produced under the legacy H(msg)-only challenge must not verify under the domain-separated one"
+        );
+    }
+
+    /// Under the legacy `e = H(msg)` challenge, `e` never depends on the public key, so the verification
+    /// equation `s·G = R + e·P` can be solved directly for an attacker-chosen `P` given arbitrary `s` and `R` -
+    /// no private key is needed. This is exactly the key-substitution forgery the domain-separated challenge
+    /// closes: the same forged `(R, s, P)` must be rejected once `e` binds `P` and `R`.
+    #[wasm_bindgen_test]
+    fn key_substitution_forgery_succeeds_under_legacy_but_fails_once_the_challenge_binds_the_key() {
+        use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+
+        let msg = "pay 100 XTR to the attacker";
+
+        let msg_hash = Blake256::digest(msg.as_bytes());
+        let mut e_bytes = [0u8; 32];
+        e_bytes.copy_from_slice(msg_hash.as_slice());
+        let e = Scalar::from_bits(e_bytes);
+
+        let s_scalar = Scalar::random(&mut OsRng);
+        let r_scalar = Scalar::random(&mut OsRng);
+        let r_point = &r_scalar * &RISTRETTO_BASEPOINT_TABLE;
+        let forged_p_point = (&s_scalar * &RISTRETTO_BASEPOINT_TABLE - r_point) * e.invert();
+
+        let forged_pub_nonce = RistrettoPublicKey::from_bytes(r_point.compress().as_bytes()).unwrap();
+        let forged_pub_key = RistrettoPublicKey::from_bytes(forged_p_point.compress().as_bytes()).unwrap();
+        let forged_signature = RistrettoSecretKey::from_bytes(s_scalar.as_bytes()).unwrap();
+
+        let legacy: SignatureVerifyResult = check_signature_legacy(
+            &forged_pub_nonce.to_hex(),
+            &forged_signature.to_hex(),
+            &forged_pub_key.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            legacy.result,
+            "the legacy challenge doesn't bind P, so an adversary can forge a signature for a public key it \
+             doesn't control, without knowing any private key"
+        );
+
+        let bound: SignatureVerifyResult = check_signature(
+            &forged_pub_nonce.to_hex(),
+            &forged_signature.to_hex(),
+            &forged_pub_key.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(!bound.result, "the domain-separated challenge binds P, so the same forged values must not verify");
+    }
+
+    fn sign_tuple(k: &RistrettoSecretKey, p: &RistrettoPublicKey, msg: &str) -> SignatureBatchTuple {
+        let signed: SignResult = sign(&k.to_hex(), msg).into_serde().unwrap();
+        SignatureBatchTuple {
+            public_nonce: signed.public_nonce.unwrap(),
+            signature: signed.signature.unwrap(),
+            public_key: p.to_hex(),
+            message: msg.to_string(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn check_signature_batch_accepts_an_all_valid_batch() {
+        let tuples: Vec<SignatureBatchTuple> = (0..10)
+            .map(|i| {
+                let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+                sign_tuple(&k, &p, &format!("message {}", i))
+            })
+            .collect();
+
+        let js_tuples = JsValue::from_serde(&tuples).unwrap();
+        let result: SignatureVerifyBatchResult = check_signature_batch(js_tuples).into_serde().unwrap();
+
+        assert!(result.result, "a batch of all-valid signatures must verify");
+        assert!(result.results.iter().all(|r| r.result));
+    }
+
+    #[wasm_bindgen_test]
+    fn check_signature_batch_falls_back_to_report_the_one_corrupted_entry() {
+        let mut tuples: Vec<SignatureBatchTuple> = (0..5)
+            .map(|i| {
+                let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+                sign_tuple(&k, &p, &format!("message {}", i))
+            })
+            .collect();
+        // Corrupt a single entry's message after signing, so its signature no longer matches its challenge.
+        tuples[2].message = "a different message entirely".to_string();
+
+        let js_tuples = JsValue::from_serde(&tuples).unwrap();
+        let result: SignatureVerifyBatchResult = check_signature_batch(js_tuples).into_serde().unwrap();
+
+        assert!(!result.result, "a batch containing an invalid signature must not verify overall");
+        for (i, r) in result.results.iter().enumerate() {
+            assert_eq!(r.result, i != 2, "only the corrupted entry (index 2) should be reported invalid");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn sign_randomized_verifies_against_the_randomized_key_but_not_the_base_key() {
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let alpha = generate_randomizer().unwrap();
+        let msg = "an unlinkable signing key";
+
+        let randomized_public_key = randomize_public(&p.to_hex(), &alpha).unwrap();
+        assert_eq!(
+            randomized_public_key,
+            randomize_secret(&k.to_hex(), &alpha)
+                .and_then(|k_prime| pubkey_from_secret(&k_prime))
+                .unwrap(),
+            "randomize_public(P, α) must equal from_secret_key(randomize_secret(k, α))"
+        );
+
+        let signed: SignResult = sign_randomized(&k.to_hex(), &alpha, msg).into_serde().unwrap();
+
+        let against_randomized: SignatureVerifyResult = check_signature(
+            signed.public_nonce.as_ref().unwrap(),
+            signed.signature.as_ref().unwrap(),
+            &randomized_public_key,
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            against_randomized.result,
+            "sign_randomized(k, α, msg) must verify against randomize_public(P, α): {}",
+            against_randomized.error
+        );
+
+        let against_base_key: SignatureVerifyResult = check_signature(
+            signed.public_nonce.as_ref().unwrap(),
+            signed.signature.as_ref().unwrap(),
+            &p.to_hex(),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(
+            !against_base_key.result,
+            "a randomized signature must not verify against the base public key, or signatures would be linkable"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn keypair_bytes_and_hex_round_trip() {
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+
+        let bytes = keypair_to_bytes(&k.to_hex()).unwrap();
+        assert_eq!(bytes.len(), KEYPAIR_LENGTH);
+        let restored: KeyPairResult = keypair_from_bytes(&bytes).into_serde().unwrap();
+        assert_eq!(restored.secret_key.as_deref(), Some(k.to_hex().as_str()));
+        assert_eq!(restored.public_key.as_deref(), Some(p.to_hex().as_str()));
+        assert!(restored.error.is_empty());
+
+        let hex = keypair_to_hex(&k.to_hex()).unwrap();
+        let restored_from_hex: KeyPairResult = keypair_from_hex(&hex).into_serde().unwrap();
+        assert_eq!(restored_from_hex.secret_key.as_deref(), Some(k.to_hex().as_str()));
+        assert_eq!(restored_from_hex.public_key.as_deref(), Some(p.to_hex().as_str()));
+    }
+
+    #[wasm_bindgen_test]
+    fn keypair_from_bytes_rejects_the_wrong_length() {
+        let result: KeyPairResult = keypair_from_bytes(&[0u8; KEYPAIR_LENGTH - 1]).into_serde().unwrap();
+        assert!(result.secret_key.is_none());
+        assert!(result.public_key.is_none());
+        assert!(!result.error.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn keypair_from_bytes_rejects_a_mismatched_public_key() {
+        let (k, _) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let (_, other_p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+
+        let mut bytes = Vec::with_capacity(KEYPAIR_LENGTH);
+        bytes.extend_from_slice(k.as_bytes());
+        bytes.extend_from_slice(other_p.as_bytes());
+
+        let result: KeyPairResult = keypair_from_bytes(&bytes).into_serde().unwrap();
+        assert!(result.secret_key.is_none());
+        assert!(result.public_key.is_none());
+        assert!(!result.error.is_empty());
+    }
+}
@@ -0,0 +1,486 @@
+// Copyright 2020. The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! MuSig2 two-round N-of-N signature aggregation over [RistrettoPublicKey]/[RistrettoSecretKey], with
+//! `wasm_bindgen` bindings mirroring the style of [super::key_utils]. The aggregated `(R, s)` signature verifies
+//! against the aggregated public key `X` as an ordinary Schnorr signature, so [super::key_utils::check_signature]
+//! applies unchanged once aggregation is complete.
+//!
+//! The flow is:
+//! 1. [musig_aggregate_keys] - combine the sorted set of signer public keys into `X`.
+//! 2. [musig_round1] - each signer generates a nonce pair and publishes the public nonces.
+//! 3. [musig_round2] - each signer computes its partial signature once every public nonce is known.
+//! 4. [musig_aggregate_partials] - combine the partial signatures into the final `(R, s)`.
+
+use std::{cell::RefCell, collections::HashSet};
+
+use crate::{
+    common::Blake256,
+    keys::PublicKey,
+    ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+};
+use blake2::Digest;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use tari_utilities::{hex::Hex, ByteArray};
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// Secret nonces that have already been consumed by [musig_round2], keyed by their raw bytes. MuSig2 is only
+    /// secure if a nonce pair is used to sign a single message; reusing one leaks the signer's secret key.
+    ///
+    /// TODO: this set is never evicted, so it grows by two 32-byte entries per `musig_round2` call for the
+    /// lifetime of the WASM instance. Fine for a short-lived signing session; a long-lived wallet/light-client
+    /// process doing many signing sessions should cap or periodically clear this (e.g. once the corresponding
+    /// signing session is known to be complete) rather than retain every nonce ever used.
+    static SPENT_NONCES: RefCell<HashSet<[u8; 32]>> = RefCell::new(HashSet::new());
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeyAggregationResult {
+    pub aggregate_public_key: Option<String>,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NonceResult {
+    pub secret_nonce_1: Option<String>,
+    pub secret_nonce_2: Option<String>,
+    pub public_nonce_1: Option<String>,
+    pub public_nonce_2: Option<String>,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PartialSignResult {
+    pub partial_signature: Option<String>,
+    pub aggregate_public_nonce: Option<String>,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AggregateSignResult {
+    pub public_nonce: Option<String>,
+    pub signature: Option<String>,
+    pub error: String,
+}
+
+/// Reduces a hash digest to a canonical Ristretto scalar via `mod l` reduction, then wraps it as a
+/// [RistrettoSecretKey]. Hashes are uniform over the full 32-byte range, while the scalar field is smaller, so a
+/// digest must always be reduced this way rather than interpreted directly as scalar bytes.
+fn hash_to_secret_key(hash: &[u8]) -> RistrettoSecretKey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(hash);
+    let scalar = Scalar::from_bytes_mod_order(bytes);
+    RistrettoSecretKey::from_bytes(scalar.as_bytes()).expect("a mod-order-reduced scalar is always canonical")
+}
+
+/// `L = H(P_1 || .. || P_n)`, the hash of every signer's public key in canonical (sorted) order.
+fn signer_hash(public_keys: &[RistrettoPublicKey]) -> Vec<u8> {
+    let mut hasher = Blake256::new();
+    for p in public_keys {
+        hasher = hasher.chain(p.as_bytes());
+    }
+    hasher.finalize().to_vec()
+}
+
+/// The per-signer key aggregation coefficient `a_i = H(L || P_i)`.
+fn key_aggregation_coefficient(signer_hash: &[u8], public_key: &RistrettoPublicKey) -> RistrettoSecretKey {
+    let hash = Blake256::new().chain(signer_hash).chain(public_key.as_bytes()).finalize();
+    hash_to_secret_key(hash.as_slice())
+}
+
+/// The aggregate public key `X = Σ a_i·P_i` for the canonical (sorted) set of signer public keys `{P_i}`.
+fn aggregate_keys(public_keys: &[RistrettoPublicKey]) -> RistrettoPublicKey {
+    let l = signer_hash(public_keys);
+    public_keys
+        .iter()
+        .map(|p| key_aggregation_coefficient(&l, p) * p)
+        .fold(RistrettoPublicKey::default(), |acc, p| acc + p)
+}
+
+fn parse_public_keys(hex: &[String]) -> Result<Vec<RistrettoPublicKey>, String> {
+    hex.iter()
+        .map(|h| RistrettoPublicKey::from_hex(h).map_err(|_| format!("{} is not a valid public key", h)))
+        .collect()
+}
+
+/// Sorts the signer set into the canonical order MuSig2 aggregation is computed over, and rejects a participant
+/// set containing the same public key more than once (a repeated key would otherwise be double-weighted in `X`).
+fn canonicalize_participants(mut public_keys: Vec<RistrettoPublicKey>) -> Result<Vec<RistrettoPublicKey>, String> {
+    public_keys.sort_by_key(|p| p.to_hex());
+    if public_keys.windows(2).any(|w| w[0] == w[1]) {
+        return Err("Participant set contains a duplicate public key".to_string());
+    }
+    Ok(public_keys)
+}
+
+/// Computes the MuSig2 aggregate public key `X` for the given set of signer public keys. The keys are sorted into
+/// a canonical order internally, so callers don't need to agree on an input order; they do need to agree on the
+/// same set of participants.
+#[wasm_bindgen]
+pub fn musig_aggregate_keys(public_keys: JsValue) -> JsValue {
+    let mut result = KeyAggregationResult {
+        aggregate_public_key: None,
+        error: "".into(),
+    };
+    let hex: Vec<String> = match public_keys.into_serde() {
+        Ok(h) => h,
+        Err(e) => {
+            result.error = format!("Could not parse public keys: {}", e);
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    if hex.is_empty() {
+        result.error = "At least one public key is required".to_string();
+        return JsValue::from_serde(&result).unwrap();
+    }
+    let keys = match parse_public_keys(&hex).and_then(canonicalize_participants) {
+        Ok(k) => k,
+        Err(e) => {
+            result.error = e;
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    result.aggregate_public_key = Some(aggregate_keys(&keys).to_hex());
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Round 1 of MuSig2 signing: generates a fresh nonce pair `(r_1, r_2)` and publishes `(R_1, R_2)`. DO NOT reuse
+/// the returned secret nonces across more than one call to [musig_round2].
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn musig_round1() -> JsValue {
+    let (r1, R1) = RistrettoPublicKey::random_keypair(&mut OsRng);
+    let (r2, R2) = RistrettoPublicKey::random_keypair(&mut OsRng);
+    let result = NonceResult {
+        secret_nonce_1: Some(r1.to_hex()),
+        secret_nonce_2: Some(r2.to_hex()),
+        public_nonce_1: Some(R1.to_hex()),
+        public_nonce_2: Some(R2.to_hex()),
+        error: "".into(),
+    };
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Marks both nonces of a round-1 pair as spent, atomically: either both are fresh and get recorded, or neither
+/// is recorded. Checking both for prior use before inserting either avoids burning a fresh nonce when the call is
+/// rejected because its sibling nonce was already used.
+fn mark_nonces_spent(r1: &RistrettoSecretKey, r2: &RistrettoSecretKey) -> Result<(), String> {
+    let mut b1 = [0u8; 32];
+    b1.copy_from_slice(r1.as_bytes());
+    let mut b2 = [0u8; 32];
+    b2.copy_from_slice(r2.as_bytes());
+    SPENT_NONCES.with(|spent| {
+        let mut spent = spent.borrow_mut();
+        if spent.contains(&b1) || spent.contains(&b2) {
+            return Err("Secret nonce has already been used and must not be reused".to_string());
+        }
+        spent.insert(b1);
+        spent.insert(b2);
+        Ok(())
+    })
+}
+
+/// Round 2 of MuSig2 signing: computes this signer's partial signature once every participant's public nonces are
+/// known. `all_public_keys`, `all_public_nonces_1` and `all_public_nonces_2` must list every N-of-N participant,
+/// with `all_public_nonces_1[i]`/`all_public_nonces_2[i]` belonging to the signer at `all_public_keys[i]`
+/// (`all_public_keys` is sorted into canonical order internally, so its input order doesn't need to match
+/// [musig_aggregate_keys]'s); `own_public_key` must be present in `all_public_keys`.
+#[allow(non_snake_case)]
+#[wasm_bindgen]
+pub fn musig_round2(
+    secret_key: &str,
+    secret_nonce_1: &str,
+    secret_nonce_2: &str,
+    own_public_key: &str,
+    all_public_keys: JsValue,
+    all_public_nonces_1: JsValue,
+    all_public_nonces_2: JsValue,
+    msg: &str,
+) -> JsValue {
+    let mut result = PartialSignResult {
+        partial_signature: None,
+        aggregate_public_nonce: None,
+        error: "".into(),
+    };
+
+    macro_rules! try_parse {
+        ($val:expr, $err:expr) => {
+            match $val {
+                Ok(v) => v,
+                Err(_) => {
+                    result.error = $err.to_string();
+                    return JsValue::from_serde(&result).unwrap();
+                },
+            }
+        };
+    }
+
+    let k = try_parse!(RistrettoSecretKey::from_hex(secret_key), "Invalid private key");
+    let r1 = try_parse!(RistrettoSecretKey::from_hex(secret_nonce_1), "Invalid secret nonce 1");
+    let r2 = try_parse!(RistrettoSecretKey::from_hex(secret_nonce_2), "Invalid secret nonce 2");
+    let P_own = try_parse!(RistrettoPublicKey::from_hex(own_public_key), "Invalid own public key");
+
+    let keys_hex: Vec<String> = try_parse!(all_public_keys.into_serde(), "Could not parse public keys");
+    let nonces1_hex: Vec<String> = try_parse!(all_public_nonces_1.into_serde(), "Could not parse public nonces 1");
+    let nonces2_hex: Vec<String> = try_parse!(all_public_nonces_2.into_serde(), "Could not parse public nonces 2");
+
+    if keys_hex.len() != nonces1_hex.len() || keys_hex.len() != nonces2_hex.len() {
+        result.error = "All participants must supply a public key and both public nonces".to_string();
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    let public_keys = try_parse!(
+        parse_public_keys(&keys_hex).and_then(canonicalize_participants),
+        "Invalid or duplicate public key in participant set"
+    );
+    if !public_keys.contains(&P_own) {
+        result.error = "Own public key is not part of the participant set".to_string();
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    let nonces_1 = try_parse!(parse_public_keys(&nonces1_hex), "Invalid public nonce in participant set");
+    let nonces_2 = try_parse!(parse_public_keys(&nonces2_hex), "Invalid public nonce in participant set");
+
+    if let Err(e) = mark_nonces_spent(&r1, &r2) {
+        result.error = e;
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    let X = aggregate_keys(&public_keys);
+    let R1 = nonces_1.into_iter().fold(RistrettoPublicKey::default(), |acc, r| acc + r);
+    let R2 = nonces_2.into_iter().fold(RistrettoPublicKey::default(), |acc, r| acc + r);
+
+    let b_hash = Blake256::new()
+        .chain(X.as_bytes())
+        .chain(R1.as_bytes())
+        .chain(R2.as_bytes())
+        .chain(msg.as_bytes())
+        .finalize();
+    let b = hash_to_secret_key(b_hash.as_slice());
+
+    let R = R1 + &b * &R2;
+    let e_hash = Blake256::new().chain(X.as_bytes()).chain(R.as_bytes()).chain(msg.as_bytes()).finalize();
+    let e = hash_to_secret_key(e_hash.as_slice());
+
+    let l = signer_hash(&public_keys);
+    let a_i = key_aggregation_coefficient(&l, &P_own);
+
+    let s_i = r1 + &b * &r2 + &e * &a_i * &k;
+
+    result.partial_signature = Some(s_i.to_hex());
+    result.aggregate_public_nonce = Some(R.to_hex());
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Aggregates every participant's partial signature from [musig_round2] into the final `(R, s)` Schnorr signature,
+/// which verifies against the aggregate public key from [musig_aggregate_keys] using ordinary Schnorr
+/// verification (see [super::key_utils::check_signature]). `public_keys` must be the same N-of-N participant set
+/// passed to [musig_aggregate_keys]/[musig_round2]: aggregation is rejected unless the number of partial
+/// signatures equals the number of participants, so a caller can't silently aggregate fewer partials than there
+/// are signers. Partial signatures are not matched to individual signers.
+#[wasm_bindgen]
+pub fn musig_aggregate_partials(
+    partial_signatures: JsValue,
+    aggregate_public_nonce: &str,
+    public_keys: JsValue,
+) -> JsValue {
+    let mut result = AggregateSignResult {
+        public_nonce: None,
+        signature: None,
+        error: "".into(),
+    };
+
+    let hex: Vec<String> = match partial_signatures.into_serde() {
+        Ok(h) => h,
+        Err(e) => {
+            result.error = format!("Could not parse partial signatures: {}", e);
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    if hex.is_empty() {
+        result.error = "At least one partial signature is required".to_string();
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    let keys_hex: Vec<String> = match public_keys.into_serde() {
+        Ok(k) => k,
+        Err(e) => {
+            result.error = format!("Could not parse public keys: {}", e);
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    let participants = match parse_public_keys(&keys_hex).and_then(canonicalize_participants) {
+        Ok(k) => k,
+        Err(e) => {
+            result.error = e;
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+    if hex.len() != participants.len() {
+        result.error = format!(
+            "Expected exactly one partial signature per participant ({}), got {}",
+            participants.len(),
+            hex.len()
+        );
+        return JsValue::from_serde(&result).unwrap();
+    }
+
+    let mut partials = Vec::with_capacity(hex.len());
+    for h in &hex {
+        match RistrettoSecretKey::from_hex(h) {
+            Ok(s) => partials.push(s),
+            Err(_) => {
+                result.error = format!("{} is not a valid partial signature", h);
+                return JsValue::from_serde(&result).unwrap();
+            },
+        }
+    }
+
+    let R = match RistrettoPublicKey::from_hex(aggregate_public_nonce) {
+        Ok(r) => r,
+        Err(_) => {
+            result.error = format!("{} is not a valid aggregate public nonce", aggregate_public_nonce);
+            return JsValue::from_serde(&result).unwrap();
+        },
+    };
+
+    let s = partials.into_iter().fold(RistrettoSecretKey::default(), |acc, s_i| acc + s_i);
+    result.public_nonce = Some(R.to_hex());
+    result.signature = Some(s.to_hex());
+    JsValue::from_serde(&result).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::{
+        keys::SecretKey,
+        wasm::key_utils::{check_signature, SignatureVerifyResult},
+    };
+
+    fn to_js(values: &[String]) -> JsValue {
+        JsValue::from_serde(values).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn two_signer_round_trip_verifies() {
+        let (k1, p1) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let (k2, p2) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let keys_hex = vec![p1.to_hex(), p2.to_hex()];
+        let msg = "musig round trip";
+
+        let agg: KeyAggregationResult = musig_aggregate_keys(to_js(&keys_hex)).into_serde().unwrap();
+        let aggregate_key = agg.aggregate_public_key.expect("key aggregation should succeed");
+
+        let n1: NonceResult = musig_round1().into_serde().unwrap();
+        let n2: NonceResult = musig_round1().into_serde().unwrap();
+        let nonces1_hex = vec![n1.public_nonce_1.clone().unwrap(), n2.public_nonce_1.clone().unwrap()];
+        let nonces2_hex = vec![n1.public_nonce_2.clone().unwrap(), n2.public_nonce_2.clone().unwrap()];
+
+        let r1: PartialSignResult = musig_round2(
+            &k1.to_hex(),
+            n1.secret_nonce_1.as_ref().unwrap(),
+            n1.secret_nonce_2.as_ref().unwrap(),
+            &p1.to_hex(),
+            to_js(&keys_hex),
+            to_js(&nonces1_hex),
+            to_js(&nonces2_hex),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+
+        let r2: PartialSignResult = musig_round2(
+            &k2.to_hex(),
+            n2.secret_nonce_1.as_ref().unwrap(),
+            n2.secret_nonce_2.as_ref().unwrap(),
+            &p2.to_hex(),
+            to_js(&keys_hex),
+            to_js(&nonces1_hex),
+            to_js(&nonces2_hex),
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+
+        assert_eq!(r1.aggregate_public_nonce, r2.aggregate_public_nonce);
+
+        let partials = vec![r1.partial_signature.unwrap(), r2.partial_signature.unwrap()];
+        let final_sig: AggregateSignResult = musig_aggregate_partials(
+            to_js(&partials),
+            r1.aggregate_public_nonce.as_ref().unwrap(),
+            to_js(&keys_hex),
+        )
+        .into_serde()
+        .unwrap();
+
+        let check: SignatureVerifyResult = check_signature(
+            final_sig.public_nonce.as_ref().unwrap(),
+            final_sig.signature.as_ref().unwrap(),
+            &aggregate_key,
+            msg,
+        )
+        .into_serde()
+        .unwrap();
+        assert!(check.result, "aggregated MuSig2 signature should verify: {}", check.error);
+    }
+
+    #[wasm_bindgen_test]
+    fn aggregate_partials_rejects_missing_participant() {
+        let (_, p1) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let (_, p2) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        let keys_hex = vec![p1.to_hex(), p2.to_hex()];
+        let one_partial = vec![RistrettoSecretKey::random(&mut OsRng).to_hex()];
+
+        let result: AggregateSignResult =
+            musig_aggregate_partials(to_js(&one_partial), &p1.to_hex(), to_js(&keys_hex))
+                .into_serde()
+                .unwrap();
+
+        assert!(result.signature.is_none());
+        assert!(!result.error.is_empty());
+    }
+
+    #[test]
+    fn rejects_reuse_of_either_sibling_nonce() {
+        let r1 = RistrettoSecretKey::random(&mut OsRng);
+        let r2 = RistrettoSecretKey::random(&mut OsRng);
+        let r3 = RistrettoSecretKey::random(&mut OsRng);
+
+        assert!(mark_nonces_spent(&r1, &r2).is_ok());
+        // Reusing either nonce from an already-spent pair must fail, and must not consume the fresh one (r3).
+        assert!(mark_nonces_spent(&r1, &r3).is_err());
+        assert!(mark_nonces_spent(&r3, &r2).is_err());
+        assert!(mark_nonces_spent(&r3, &RistrettoSecretKey::random(&mut OsRng)).is_ok());
+    }
+
+    #[test]
+    fn canonicalize_participants_rejects_duplicates() {
+        let (_, p) = RistrettoPublicKey::random_keypair(&mut OsRng);
+        assert!(canonicalize_participants(vec![p.clone(), p]).is_err());
+    }
+}